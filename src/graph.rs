@@ -0,0 +1,40 @@
+use std::collections::HashSet;
+
+/// A node in an autodiff computation graph, abstracting over `value::Value`
+/// and `tensor::Tensor` so their graph traversals (`topological_sort`,
+/// `trace`) share one implementation instead of being duplicated per type.
+///
+/// `identity` must return the address of the node's underlying `Rc`, not
+/// anything derived from its label: two distinct nodes may legitimately
+/// share a label, and the same node may legitimately be reached more than
+/// once (e.g. through a tied weight), so traversal has to dedup on identity.
+pub(crate) trait GraphNode: Clone {
+    fn identity(&self) -> usize;
+    fn prev(&self) -> Vec<Self>;
+}
+
+pub(crate) fn topological_sort<N: GraphNode>(root: N) -> Vec<N> {
+    fn rec<N: GraphNode>(val: N, visited: &mut HashSet<usize>, sorted: &mut Vec<N>) {
+        if visited.insert(val.identity()) {
+            for prev in val.prev() {
+                rec(prev, visited, sorted);
+            }
+            sorted.push(val);
+        }
+    }
+    let mut sorted = Vec::new();
+    rec(root, &mut HashSet::new(), &mut sorted);
+    sorted
+}
+
+pub(crate) fn trace<N: GraphNode + std::fmt::Display>(root: &N) {
+    fn rec<N: GraphNode + std::fmt::Display>(val: &N, visited: &mut HashSet<usize>, depth: usize) {
+        if visited.insert(val.identity()) {
+            println!("{}{}", "|   ".repeat(depth), val);
+            for child in &val.prev() {
+                rec(child, visited, depth + 1);
+            }
+        }
+    }
+    rec(root, &mut HashSet::new(), 0);
+}
@@ -0,0 +1,92 @@
+/// Builds a [`Value`](crate::value::Value) graph from an ordinary Rust
+/// arithmetic expression instead of chained `.mul()`/`.add()` calls:
+///
+/// ```ignore
+/// let o = autodiff!(x, w, b => (x * w + b).tanh());
+/// o.backward();
+/// // x.grad(), w.grad(), b.grad() are now populated.
+/// ```
+///
+/// Each name before `=>` is rebound to a reference to the `Value` of the
+/// same name already in scope, so it can appear more than once in the
+/// expression without being moved. The expression itself is plain Rust:
+/// `+`, `-`, `*`, `/`, `.powf(n)`, and the activation methods (`.tanh()`,
+/// `.sigmoid()`, `.relu()`, `.exp()`, `.ln()`) all work directly, and rustc
+/// parses the expression with its usual precedence instead of this macro
+/// reimplementing one. The graph built is exactly the one the equivalent
+/// chain of `.mul()`/`.add()` calls would build, so `.backward()` on the
+/// result populates every variable's `.grad()` as usual.
+///
+/// This macro does no common-subexpression elimination: writing the same
+/// subexpression twice (e.g. `(x * w).tanh() + (x * w)`) builds two separate
+/// multiply nodes, one per occurrence, exactly as the equivalent
+/// `.mul()`/`.add()` chain would. To reuse a subexpression as a single
+/// shared node instead, bind it with `let` in a block body yourself, the
+/// same as in ordinary Rust:
+///
+/// ```ignore
+/// let o = autodiff!(x, w => {
+///     let xw = x * w;
+///     xw.tanh() + xw
+/// });
+/// ```
+#[macro_export]
+macro_rules! autodiff {
+    ($($var:ident),+ $(,)? => $body:expr) => {{
+        $(let $var = &$var;)+
+        $body
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::value::Value;
+
+    fn float_equal(x: f64, y: f64) -> bool {
+        (x - y).abs() < 0.001
+    }
+
+    #[test]
+    fn test_autodiff_matches_manual_graph() {
+        let x1 = Value::with_label(2.0, "x1");
+        let x2 = Value::with_label(0.0, "x2");
+        let w1 = Value::with_label(-3.0, "w1");
+        let w2 = Value::with_label(1.0, "w2");
+        let b = Value::with_label(6.881373587019543, "b");
+
+        let mut o = autodiff!(x1, x2, w1, w2, b => (x1 * w1 + x2 * w2 + b).tanh());
+        assert!(float_equal(o.val(), 0.7071));
+        o.backward();
+
+        assert!(float_equal(x1.grad(), -1.5));
+        assert!(float_equal(w1.grad(), 1.0));
+        assert!(float_equal(x2.grad(), 0.5));
+        assert!(float_equal(w2.grad(), 0.0));
+    }
+
+    #[test]
+    fn test_autodiff_reused_variable() {
+        let x = Value::with_label(3.0, "x");
+        let mut y = autodiff!(x => x * x + x);
+        assert!(float_equal(y.val(), 12.0));
+        y.backward();
+        assert!(float_equal(x.grad(), 7.0));
+    }
+
+    #[test]
+    fn test_autodiff_shared_subexpression() {
+        let x = Value::with_label(2.0, "x");
+        let w = Value::with_label(5.0, "w");
+        let mut o = autodiff!(x, w => {
+            let xw = x * w;
+            xw.tanh() + xw
+        });
+        o.backward();
+
+        // do/d(xw) = (1 - tanh(xw)^2) + 1, with xw = 10
+        let xw = 10.0;
+        let d_o_d_xw = (1.0 - f64::tanh(xw).powi(2)) + 1.0;
+        assert!(float_equal(x.grad(), d_o_d_xw * w.val()));
+        assert!(float_equal(w.grad(), d_o_d_xw * x.val()));
+    }
+}
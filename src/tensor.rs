@@ -0,0 +1,351 @@
+use std::{
+    cell::{Ref, RefCell},
+    fmt,
+    rc::Rc,
+};
+
+use ndarray::{Array2, ArrayD, Axis, IxDyn};
+
+use crate::graph::GraphNode;
+
+/// The underlying N-dimensional array backing a [`Tensor`]'s value and
+/// gradient.
+pub type NdArray = ArrayD<f64>;
+
+/// A differentiable tensor operation: the [`value::Op`](crate::value::Op)
+/// trait, but over whole `NdArray`s instead of scalars.
+pub trait TensorOp: fmt::Debug {
+    fn forward(&self, inputs: &[Tensor]) -> NdArray;
+    fn backward(&self, out_grad: &NdArray, inputs: &[Tensor]);
+    fn inputs(&self) -> Vec<Tensor>;
+}
+
+/// Sums `grad` down to `shape` by reducing over the axes that were
+/// broadcast during the forward pass (NumPy broadcasting-gradient rule).
+fn reduce_to_shape(grad: &NdArray, shape: &[usize]) -> NdArray {
+    let mut grad = grad.clone();
+    while grad.ndim() > shape.len() {
+        grad = grad.sum_axis(Axis(0));
+    }
+    for (axis, &dim) in shape.iter().enumerate() {
+        if dim == 1 && grad.shape()[axis] != 1 {
+            grad = grad.sum_axis(Axis(axis)).insert_axis(Axis(axis));
+        }
+    }
+    grad.into_shape(IxDyn(shape))
+        .expect("broadcast-reduced gradient shape mismatch")
+}
+
+fn to_2d(arr: &NdArray) -> Array2<f64> {
+    arr.clone()
+        .into_dimensionality::<ndarray::Ix2>()
+        .expect("matmul operands must be 2-D")
+}
+
+#[derive(Debug)]
+struct AddOp {
+    lhs: Tensor,
+    rhs: Tensor,
+}
+
+impl TensorOp for AddOp {
+    fn forward(&self, inputs: &[Tensor]) -> NdArray {
+        &inputs[0].val() + &inputs[1].val()
+    }
+
+    fn backward(&self, out_grad: &NdArray, inputs: &[Tensor]) {
+        inputs[0].accumulate_grad(&reduce_to_shape(out_grad, &inputs[0].shape()));
+        inputs[1].accumulate_grad(&reduce_to_shape(out_grad, &inputs[1].shape()));
+    }
+
+    fn inputs(&self) -> Vec<Tensor> {
+        vec![self.lhs.clone(), self.rhs.clone()]
+    }
+}
+
+#[derive(Debug)]
+struct MulOp {
+    lhs: Tensor,
+    rhs: Tensor,
+}
+
+impl TensorOp for MulOp {
+    fn forward(&self, inputs: &[Tensor]) -> NdArray {
+        &inputs[0].val() * &inputs[1].val()
+    }
+
+    fn backward(&self, out_grad: &NdArray, inputs: &[Tensor]) {
+        let lhs_grad = out_grad * &inputs[1].val();
+        let rhs_grad = out_grad * &inputs[0].val();
+        inputs[0].accumulate_grad(&reduce_to_shape(&lhs_grad, &inputs[0].shape()));
+        inputs[1].accumulate_grad(&reduce_to_shape(&rhs_grad, &inputs[1].shape()));
+    }
+
+    fn inputs(&self) -> Vec<Tensor> {
+        vec![self.lhs.clone(), self.rhs.clone()]
+    }
+}
+
+#[derive(Debug)]
+struct MatMulOp {
+    lhs: Tensor,
+    rhs: Tensor,
+}
+
+impl TensorOp for MatMulOp {
+    fn forward(&self, inputs: &[Tensor]) -> NdArray {
+        to_2d(&inputs[0].val()).dot(&to_2d(&inputs[1].val())).into_dyn()
+    }
+
+    fn backward(&self, out_grad: &NdArray, inputs: &[Tensor]) {
+        let a = to_2d(&inputs[0].val());
+        let b = to_2d(&inputs[1].val());
+        let g = to_2d(out_grad);
+        inputs[0].accumulate_grad(&g.dot(&b.t()).into_dyn());
+        inputs[1].accumulate_grad(&a.t().dot(&g).into_dyn());
+    }
+
+    fn inputs(&self) -> Vec<Tensor> {
+        vec![self.lhs.clone(), self.rhs.clone()]
+    }
+}
+
+#[derive(Debug)]
+struct SumOp {
+    arg: Tensor,
+}
+
+impl TensorOp for SumOp {
+    fn forward(&self, inputs: &[Tensor]) -> NdArray {
+        ArrayD::from_elem(IxDyn(&[]), inputs[0].val().sum())
+    }
+
+    fn backward(&self, out_grad: &NdArray, inputs: &[Tensor]) {
+        let g = out_grad.sum();
+        inputs[0].accumulate_grad(&ArrayD::from_elem(IxDyn(&inputs[0].shape()), g));
+    }
+
+    fn inputs(&self) -> Vec<Tensor> {
+        vec![self.arg.clone()]
+    }
+}
+
+#[derive(Debug)]
+struct MeanOp {
+    arg: Tensor,
+}
+
+impl TensorOp for MeanOp {
+    fn forward(&self, inputs: &[Tensor]) -> NdArray {
+        ArrayD::from_elem(IxDyn(&[]), inputs[0].val().mean().unwrap_or(0.0))
+    }
+
+    fn backward(&self, out_grad: &NdArray, inputs: &[Tensor]) {
+        let n = inputs[0].val().len() as f64;
+        let g = out_grad.sum() / n;
+        inputs[0].accumulate_grad(&ArrayD::from_elem(IxDyn(&inputs[0].shape()), g));
+    }
+
+    fn inputs(&self) -> Vec<Tensor> {
+        vec![self.arg.clone()]
+    }
+}
+
+struct TensorInternal {
+    val: NdArray,
+    grad: NdArray,
+    label: String,
+    op: Option<Box<dyn TensorOp>>,
+}
+
+impl fmt::Debug for TensorInternal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TensorInternal")
+            .field("val", &self.val)
+            .field("grad", &self.grad)
+            .field("label", &self.label)
+            .field("op", &self.op.is_some())
+            .finish()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Tensor(Rc<RefCell<TensorInternal>>);
+
+impl GraphNode for Tensor {
+    fn identity(&self) -> usize {
+        Rc::as_ptr(&self.0) as usize
+    }
+
+    fn prev(&self) -> Vec<Tensor> {
+        match &self.0.borrow().op {
+            None => vec![],
+            Some(op) => op.inputs(),
+        }
+    }
+}
+
+impl Tensor {
+    pub fn of(val: NdArray) -> Tensor {
+        Tensor::with_label(val, "tensor")
+    }
+
+    pub fn with_label(val: NdArray, label: impl Into<String>) -> Tensor {
+        let grad = ArrayD::zeros(val.raw_dim());
+        Tensor(Rc::new(RefCell::new(TensorInternal {
+            val,
+            grad,
+            label: label.into(),
+            op: None,
+        })))
+    }
+
+    fn from_op(label: String, op: Box<dyn TensorOp>) -> Tensor {
+        let val = op.forward(&op.inputs());
+        let grad = ArrayD::zeros(val.raw_dim());
+        Tensor(Rc::new(RefCell::new(TensorInternal { val, grad, label, op: Some(op) })))
+    }
+
+    pub fn add(&self, rhs: &Tensor) -> Tensor {
+        let label = format!("{} + {}", self.label(), rhs.label());
+        Tensor::from_op(label, Box::new(AddOp { lhs: self.clone(), rhs: rhs.clone() }))
+    }
+
+    pub fn mul(&self, rhs: &Tensor) -> Tensor {
+        let label = format!("{}*{}", self.label(), rhs.label());
+        Tensor::from_op(label, Box::new(MulOp { lhs: self.clone(), rhs: rhs.clone() }))
+    }
+
+    pub fn matmul(&self, rhs: &Tensor) -> Tensor {
+        let label = format!("{}@{}", self.label(), rhs.label());
+        Tensor::from_op(label, Box::new(MatMulOp { lhs: self.clone(), rhs: rhs.clone() }))
+    }
+
+    pub fn sum(&self) -> Tensor {
+        let label = format!("sum({})", self.label());
+        Tensor::from_op(label, Box::new(SumOp { arg: self.clone() }))
+    }
+
+    pub fn mean(&self) -> Tensor {
+        let label = format!("mean({})", self.label());
+        Tensor::from_op(label, Box::new(MeanOp { arg: self.clone() }))
+    }
+
+    pub fn val(&self) -> NdArray {
+        self.0.borrow().val.clone()
+    }
+
+    pub fn grad(&self) -> NdArray {
+        self.0.borrow().grad.clone()
+    }
+
+    pub fn shape(&self) -> Vec<usize> {
+        self.0.borrow().val.shape().to_vec()
+    }
+
+    pub fn label(&self) -> Ref<'_, String> {
+        Ref::map(self.0.borrow(), |r| &r.label)
+    }
+
+    fn accumulate_grad(&self, grad: &NdArray) {
+        self.0.borrow_mut().grad += grad;
+    }
+
+    fn local_backward(&mut self) {
+        let grad = self.grad();
+        if let Some(op) = &self.0.borrow().op {
+            op.backward(&grad, &op.inputs());
+        }
+    }
+
+    pub fn backward(&mut self) {
+        let shape = self.0.borrow().val.raw_dim();
+        self.0.borrow_mut().grad = ArrayD::ones(shape);
+        let mut nodes = crate::graph::topological_sort(self.clone());
+        nodes.reverse();
+        for mut node in nodes {
+            node.local_backward();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::array;
+
+    fn float_equal(x: f64, y: f64) -> bool {
+        (x - y).abs() < 0.001
+    }
+
+    #[test]
+    fn test_add_broadcast_backward() {
+        let a = Tensor::with_label(array![[1.0, 2.0], [3.0, 4.0]].into_dyn(), "a");
+        let b = Tensor::with_label(array![10.0, 20.0].into_dyn(), "b");
+        let mut c = a.add(&b);
+        assert_eq!(c.val(), array![[11.0, 22.0], [13.0, 24.0]].into_dyn());
+
+        c.backward();
+        assert_eq!(a.grad(), array![[1.0, 1.0], [1.0, 1.0]].into_dyn());
+        assert_eq!(b.grad(), array![2.0, 2.0].into_dyn());
+    }
+
+    #[test]
+    fn test_mul_broadcast_backward() {
+        let a = Tensor::with_label(array![[1.0, 2.0], [3.0, 4.0]].into_dyn(), "a");
+        let b = Tensor::with_label(array![10.0, 20.0].into_dyn(), "b");
+        let mut c = a.mul(&b);
+        assert_eq!(c.val(), array![[10.0, 40.0], [30.0, 80.0]].into_dyn());
+
+        c.backward();
+        assert_eq!(a.grad(), array![[10.0, 20.0], [10.0, 20.0]].into_dyn());
+        assert_eq!(b.grad(), array![4.0, 6.0].into_dyn());
+    }
+
+    #[test]
+    fn test_mean_backward() {
+        let a = Tensor::with_label(array![[1.0, 2.0], [3.0, 4.0]].into_dyn(), "a");
+        let mut loss = a.mean();
+        assert!(float_equal(loss.val()[[]], 2.5));
+
+        loss.backward();
+        assert_eq!(a.grad(), array![[0.25, 0.25], [0.25, 0.25]].into_dyn());
+    }
+
+    #[test]
+    fn test_matmul_backward() {
+        let a = Tensor::with_label(array![[1.0, 2.0], [3.0, 4.0]].into_dyn(), "a");
+        let b = Tensor::with_label(array![[5.0, 6.0], [7.0, 8.0]].into_dyn(), "b");
+        let mut loss = a.matmul(&b).sum();
+        assert!(float_equal(loss.val()[[]], 134.0));
+
+        loss.backward();
+        assert_eq!(a.grad(), array![[11.0, 15.0], [11.0, 15.0]].into_dyn());
+        assert_eq!(b.grad(), array![[4.0, 4.0], [6.0, 6.0]].into_dyn());
+    }
+
+    #[test]
+    fn test_backward_with_duplicate_labels() {
+        // `Tensor::of` hands every tensor the same default label, so two
+        // independently-built subgraphs end up with identical derived
+        // labels too (both "tensor + tensor" here). Traversal must still
+        // visit both instead of treating the second as already seen.
+        let a1 = Tensor::of(array![1.0].into_dyn());
+        let b1 = Tensor::of(array![2.0].into_dyn());
+        let combo1 = a1.add(&b1);
+
+        let a2 = Tensor::of(array![3.0].into_dyn());
+        let b2 = Tensor::of(array![4.0].into_dyn());
+        let combo2 = a2.add(&b2);
+
+        assert_eq!(*combo1.label(), *combo2.label());
+
+        let mut out = combo1.add(&combo2);
+        out.backward();
+
+        assert_eq!(a1.grad(), array![1.0].into_dyn());
+        assert_eq!(b1.grad(), array![1.0].into_dyn());
+        assert_eq!(a2.grad(), array![1.0].into_dyn());
+        assert_eq!(b2.grad(), array![1.0].into_dyn());
+    }
+}
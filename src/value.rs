@@ -1,24 +1,201 @@
 use std::{
     cell::{Ref, RefCell},
-    collections::HashSet,
     rc::Rc,
 };
 
+use crate::graph::GraphNode;
+
+/// A differentiable operation: given its inputs, computes a forward value and
+/// knows how to push a gradient back onto each input.
+///
+/// Implementing this trait lets callers outside this crate define their own
+/// ops (e.g. a Gaussian or a softplus) and drop them into the graph, since
+/// `topological_sort`, `backward`, and `trace` only ever go through these
+/// methods and never match on a concrete op type.
+pub trait Op: std::fmt::Debug {
+    fn forward(&self, inputs: &[Value]) -> f64;
+    fn backward(&self, out_grad: f64, inputs: &[Value]);
+    fn inputs(&self) -> Vec<Value>;
+}
+
+#[derive(Debug)]
+struct AddOp {
+    lhs: Value,
+    rhs: Value,
+}
+
+impl Op for AddOp {
+    fn forward(&self, inputs: &[Value]) -> f64 {
+        inputs[0].val() + inputs[1].val()
+    }
+
+    fn backward(&self, out_grad: f64, inputs: &[Value]) {
+        inputs[0].accumulate_grad(out_grad);
+        inputs[1].accumulate_grad(out_grad);
+    }
+
+    fn inputs(&self) -> Vec<Value> {
+        vec![self.lhs.clone(), self.rhs.clone()]
+    }
+}
+
+#[derive(Debug)]
+struct MulOp {
+    lhs: Value,
+    rhs: Value,
+}
+
+impl Op for MulOp {
+    fn forward(&self, inputs: &[Value]) -> f64 {
+        inputs[0].val() * inputs[1].val()
+    }
+
+    fn backward(&self, out_grad: f64, inputs: &[Value]) {
+        inputs[0].accumulate_grad(inputs[1].val() * out_grad);
+        inputs[1].accumulate_grad(inputs[0].val() * out_grad);
+    }
+
+    fn inputs(&self) -> Vec<Value> {
+        vec![self.lhs.clone(), self.rhs.clone()]
+    }
+}
+
 #[derive(Debug)]
-pub enum Op {
-    None,
-    Add(Value, Value),
-    Mul(Value, Value),
-    Tanh(Value),
-    Pow(Value, f64),
+struct TanhOp {
+    arg: Value,
+}
+
+impl Op for TanhOp {
+    fn forward(&self, inputs: &[Value]) -> f64 {
+        let e2x = f64::exp(2.0 * inputs[0].val());
+        (e2x - 1.0) / (e2x + 1.0)
+    }
+
+    fn backward(&self, out_grad: f64, inputs: &[Value]) {
+        let t = self.forward(inputs);
+        inputs[0].accumulate_grad((1.0 - t.powf(2.0)) * out_grad);
+    }
+
+    fn inputs(&self) -> Vec<Value> {
+        vec![self.arg.clone()]
+    }
 }
 
 #[derive(Debug)]
+struct PowOp {
+    base: Value,
+    exp: f64,
+}
+
+impl Op for PowOp {
+    fn forward(&self, inputs: &[Value]) -> f64 {
+        inputs[0].val().powf(self.exp)
+    }
+
+    fn backward(&self, out_grad: f64, inputs: &[Value]) {
+        inputs[0].accumulate_grad(self.exp * inputs[0].val().powf(self.exp - 1.0) * out_grad);
+    }
+
+    fn inputs(&self) -> Vec<Value> {
+        vec![self.base.clone()]
+    }
+}
+
+#[derive(Debug)]
+struct SigmoidOp {
+    arg: Value,
+}
+
+impl Op for SigmoidOp {
+    fn forward(&self, inputs: &[Value]) -> f64 {
+        1.0 / (1.0 + f64::exp(-inputs[0].val()))
+    }
+
+    fn backward(&self, out_grad: f64, inputs: &[Value]) {
+        let s = self.forward(inputs);
+        inputs[0].accumulate_grad(s * (1.0 - s) * out_grad);
+    }
+
+    fn inputs(&self) -> Vec<Value> {
+        vec![self.arg.clone()]
+    }
+}
+
+#[derive(Debug)]
+struct ReluOp {
+    arg: Value,
+}
+
+impl Op for ReluOp {
+    fn forward(&self, inputs: &[Value]) -> f64 {
+        inputs[0].val().max(0.0)
+    }
+
+    fn backward(&self, out_grad: f64, inputs: &[Value]) {
+        if inputs[0].val() > 0.0 {
+            inputs[0].accumulate_grad(out_grad);
+        }
+    }
+
+    fn inputs(&self) -> Vec<Value> {
+        vec![self.arg.clone()]
+    }
+}
+
+#[derive(Debug)]
+struct ExpOp {
+    arg: Value,
+}
+
+impl Op for ExpOp {
+    fn forward(&self, inputs: &[Value]) -> f64 {
+        inputs[0].val().exp()
+    }
+
+    fn backward(&self, out_grad: f64, inputs: &[Value]) {
+        inputs[0].accumulate_grad(self.forward(inputs) * out_grad);
+    }
+
+    fn inputs(&self) -> Vec<Value> {
+        vec![self.arg.clone()]
+    }
+}
+
+#[derive(Debug)]
+struct LnOp {
+    arg: Value,
+}
+
+impl Op for LnOp {
+    fn forward(&self, inputs: &[Value]) -> f64 {
+        inputs[0].val().ln()
+    }
+
+    fn backward(&self, out_grad: f64, inputs: &[Value]) {
+        inputs[0].accumulate_grad(out_grad / inputs[0].val());
+    }
+
+    fn inputs(&self) -> Vec<Value> {
+        vec![self.arg.clone()]
+    }
+}
+
 struct ValueInternal {
     val: f64,
     grad: f64,
     label: String,
-    op: Op,
+    op: Option<Box<dyn Op>>,
+}
+
+impl std::fmt::Debug for ValueInternal {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ValueInternal")
+            .field("val", &self.val)
+            .field("grad", &self.grad)
+            .field("label", &self.label)
+            .field("op", &self.op.is_some())
+            .finish()
+    }
 }
 
 impl std::fmt::Display for ValueInternal {
@@ -40,21 +217,17 @@ impl std::fmt::Display for Value {
     }
 }
 
-fn topological_sort(root: Value) -> Vec<Value> {
-    fn rec(val: Value, v: &mut HashSet<String>, sorted: &mut Vec<Value>) {
-        // can we avoid using the labels for the set here?
-        if !v.contains(val.label().as_str()) {
-            v.insert(val.label().to_string());
-            for prev in val.prev() {
-                // TODO: eliminate clone
-                rec(prev.clone(), v, sorted);
-            }
-            sorted.push(val);
+impl GraphNode for Value {
+    fn identity(&self) -> usize {
+        Rc::as_ptr(&self.0) as usize
+    }
+
+    fn prev(&self) -> Vec<Value> {
+        match &self.0.borrow().op {
+            None => vec![],
+            Some(op) => op.inputs(),
         }
     }
-    let mut sorted = Vec::new();
-    rec(root, &mut HashSet::new(), &mut sorted);
-    sorted
 }
 
 impl Value {
@@ -63,45 +236,94 @@ impl Value {
     }
 
     pub fn with_label(val: f64, label: impl Into<String>) -> Value {
-        Value::with_op(val, label.into(), Op::None)
+        Value(Rc::new(RefCell::new(ValueInternal {
+            val,
+            grad: 0.0,
+            label: label.into(),
+            op: None,
+        })))
     }
 
-    fn with_op(val: f64, label: String, op: Op) -> Value {
-        let val = ValueInternal { val, grad: 0.0, label, op };
-        Value(Rc::new(RefCell::new(val)))
+    /// Builds a `Value` whose forward value comes from `op.forward(...)` and
+    /// whose `backward()` pushes gradients through `op.backward(...)`.
+    ///
+    /// This is how a caller outside this crate attaches a custom [`Op`] to
+    /// the graph: implement `Op` for your type, then pass a `Box` of it here
+    /// to get back an ordinary `Value` that composes with every other method
+    /// on this type.
+    pub fn from_op(label: String, op: Box<dyn Op>) -> Value {
+        let val = op.forward(&op.inputs());
+        Value(Rc::new(RefCell::new(ValueInternal {
+            val,
+            grad: 0.0,
+            label,
+            op: Some(op),
+        })))
     }
 
     pub fn tanh(&self) -> Value {
-        let e2x = f64::exp(2.0 * self.val());
-        let val = (e2x - 1.0) / (e2x + 1.0);
         let label = format!("tanh({})", self.label());
-        Value::with_op(val, label, Op::Tanh(self.clone()))
+        Value::from_op(label, Box::new(TanhOp { arg: self.clone() }))
     }
 
     // using operator overloading would require taking references everywhere,
     // e.g. &x + &y because we can't make ValueRef Copy :(
     pub fn mul(&self, rhs: &Value) -> Value {
-        let val = self.val() * rhs.val();
         let label = format!("{}*{}", self.label(), rhs.label());
-        Value::with_op(val, label, Op::Mul(self.clone(), rhs.clone()))
+        Value::from_op(
+            label,
+            Box::new(MulOp { lhs: self.clone(), rhs: rhs.clone() }),
+        )
     }
 
     pub fn add(&self, rhs: &Value) -> Value {
-        let val = self.val() + rhs.val();
         let label = format!("{} + {}", self.label(), rhs.label());
-        Value::with_op(val, label, Op::Add(self.clone(), rhs.clone()))
+        Value::from_op(
+            label,
+            Box::new(AddOp { lhs: self.clone(), rhs: rhs.clone() }),
+        )
     }
 
     pub fn pow(&self, arg: f64) -> Value {
-        let val = self.val().powf(arg);
         let label = format!("{}^{}", self.label(), arg);
-        Value::with_op(val, label, Op::Pow(self.clone(), arg))
+        Value::from_op(label, Box::new(PowOp { base: self.clone(), exp: arg }))
+    }
+
+    /// Alias for [`Value::pow`] matching `f64::powf`'s name, so expressions
+    /// written inside [`autodiff!`](crate::autodiff) read like ordinary
+    /// float arithmetic.
+    pub fn powf(&self, arg: f64) -> Value {
+        self.pow(arg)
     }
 
     pub fn sub(&self, rhs: &Value) -> Value {
         self.add(&rhs.mul(&Value::of(-1.0)))
     }
 
+    pub fn div(&self, rhs: &Value) -> Value {
+        self.mul(&rhs.pow(-1.0))
+    }
+
+    pub fn sigmoid(&self) -> Value {
+        let label = format!("sigmoid({})", self.label());
+        Value::from_op(label, Box::new(SigmoidOp { arg: self.clone() }))
+    }
+
+    pub fn relu(&self) -> Value {
+        let label = format!("relu({})", self.label());
+        Value::from_op(label, Box::new(ReluOp { arg: self.clone() }))
+    }
+
+    pub fn exp(&self) -> Value {
+        let label = format!("exp({})", self.label());
+        Value::from_op(label, Box::new(ExpOp { arg: self.clone() }))
+    }
+
+    pub fn ln(&self) -> Value {
+        let label = format!("ln({})", self.label());
+        Value::from_op(label, Box::new(LnOp { arg: self.clone() }))
+    }
+
     pub fn val(&self) -> f64 {
         self.0.borrow().val
     }
@@ -118,66 +340,80 @@ impl Value {
         self.0.borrow_mut().grad = 0.0;
     }
 
+    /// Adds `by` onto this node's accumulated gradient. An [`Op::backward`]
+    /// implementation calls this on each of its inputs to push the
+    /// upstream gradient through the chain rule; it takes `&self` rather
+    /// than `&mut self` since a node may be reached through more than one
+    /// path (e.g. a tied weight) and each path only holds a shared reference.
+    pub fn accumulate_grad(&self, by: f64) {
+        self.0.borrow_mut().grad += by;
+    }
+
     pub fn label(&self) -> Ref<'_, String> {
         Ref::map(self.0.borrow(), |r| &r.label)
     }
 
     fn local_backward(&mut self) {
-        match &self.0.borrow().op {
-            Op::None => {}
-            Op::Add(lhs, rhs) => {
-                lhs.0.borrow_mut().grad += self.grad();
-                rhs.0.borrow_mut().grad += self.grad();
-            }
-            Op::Mul(lhs, rhs) => {
-                lhs.0.borrow_mut().grad += rhs.val() * self.grad();
-                rhs.0.borrow_mut().grad += lhs.val() * self.grad();
-            }
-            Op::Tanh(arg) => {
-                let e2x = f64::exp(2.0 * arg.val());
-                let t = (e2x - 1.0) / (e2x + 1.0);
-                arg.0.borrow_mut().grad += (1.0 - t.powf(2.0)) * self.grad();
-            }
-            Op::Pow(base, exp) => {
-                base.0.borrow_mut().grad +=
-                    exp * base.val().powf(exp - 1.0) * self.grad();
-            }
+        let grad = self.grad();
+        if let Some(op) = &self.0.borrow().op {
+            op.backward(grad, &op.inputs());
         }
     }
 
     pub fn backward(&mut self) {
         self.0.borrow_mut().grad = 1.0;
-        let mut nodes = topological_sort(self.clone());
+        let mut nodes = crate::graph::topological_sort(self.clone());
         nodes.reverse();
         for mut node in nodes {
             node.local_backward();
         }
     }
+}
 
-    fn prev(&self) -> Vec<Value> {
-        match &self.0.borrow().op {
-            Op::None => vec![],
-            Op::Add(lhs, rhs) | Op::Mul(lhs, rhs) => {
-                vec![lhs.clone(), rhs.clone()]
+// `Value` doesn't implement these operators as the primary API (see the
+// note on `mul` above), but `autodiff!` expands ordinary arithmetic into
+// calls through them, so every combination of by-value/by-reference
+// operands needs to be covered, the same way the standard library's
+// numeric types do.
+macro_rules! impl_value_binop {
+    ($trait:ident, $method:ident) => {
+        impl std::ops::$trait<&Value> for &Value {
+            type Output = Value;
+            fn $method(self, rhs: &Value) -> Value {
+                Value::$method(self, rhs)
             }
-            Op::Tanh(arg) => vec![arg.clone()],
-            Op::Pow(base, _) => vec![base.clone()],
         }
-    }
-}
 
-pub fn trace(val: &Value) {
-    fn trace(val: &Value, v: &mut HashSet<String>, n: usize) {
-        if !v.contains(val.label().as_str()) {
-            v.insert(val.label().to_string());
-            let padding = "|   ".repeat(n);
-            println!("{}{}", padding, val);
-            for child in &val.prev() {
-                trace(child, v, n + 1);
+        impl std::ops::$trait<Value> for &Value {
+            type Output = Value;
+            fn $method(self, rhs: Value) -> Value {
+                Value::$method(self, &rhs)
             }
         }
-    }
-    trace(val, &mut HashSet::new(), 0);
+
+        impl std::ops::$trait<&Value> for Value {
+            type Output = Value;
+            fn $method(self, rhs: &Value) -> Value {
+                Value::$method(&self, rhs)
+            }
+        }
+
+        impl std::ops::$trait<Value> for Value {
+            type Output = Value;
+            fn $method(self, rhs: Value) -> Value {
+                Value::$method(&self, &rhs)
+            }
+        }
+    };
+}
+
+impl_value_binop!(Add, add);
+impl_value_binop!(Sub, sub);
+impl_value_binop!(Mul, mul);
+impl_value_binop!(Div, div);
+
+pub fn trace(val: &Value) {
+    crate::graph::trace(val)
 }
 
 #[cfg(test)]
@@ -227,4 +463,119 @@ mod tests {
         assert!(float_equal(x2.grad(), 0.5));
         assert!(float_equal(w2.grad(), 0.0));
     }
+
+    #[test]
+    fn test_operator_overloads_match_method_calls() {
+        let a = Value::with_label(2.0, "a");
+        let b = Value::with_label(3.0, "b");
+
+        assert!(float_equal((&a + &b).val(), a.add(&b).val()));
+        assert!(float_equal((&a - &b).val(), a.sub(&b).val()));
+        assert!(float_equal((&a * &b).val(), a.mul(&b).val()));
+        assert!(float_equal((&a / &b).val(), a.div(&b).val()));
+    }
+
+    #[test]
+    fn test_sigmoid() {
+        let x = Value::with_label(0.0, "x");
+        let mut s = x.sigmoid();
+        assert!(float_equal(s.val(), 0.5));
+        s.backward();
+        assert!(float_equal(x.grad(), 0.25));
+    }
+
+    #[test]
+    fn test_relu() {
+        let pos = Value::with_label(3.0, "pos");
+        let mut pos_out = pos.relu();
+        assert!(float_equal(pos_out.val(), 3.0));
+        pos_out.backward();
+        assert!(float_equal(pos.grad(), 1.0));
+
+        let neg = Value::with_label(-3.0, "neg");
+        let mut neg_out = neg.relu();
+        assert!(float_equal(neg_out.val(), 0.0));
+        neg_out.backward();
+        assert!(float_equal(neg.grad(), 0.0));
+    }
+
+    #[test]
+    fn test_exp_ln() {
+        let x = Value::with_label(2.0, "x");
+        let mut e = x.exp();
+        assert!(float_equal(e.val(), f64::exp(2.0)));
+        e.backward();
+        assert!(float_equal(x.grad(), f64::exp(2.0)));
+
+        let y = Value::with_label(2.0, "y");
+        let mut l = y.ln();
+        assert!(float_equal(l.val(), f64::ln(2.0)));
+        l.backward();
+        assert!(float_equal(y.grad(), 0.5));
+    }
+
+    #[test]
+    fn test_div() {
+        let a = Value::with_label(6.0, "a");
+        let b = Value::with_label(2.0, "b");
+        let mut q = a.div(&b);
+        assert!(float_equal(q.val(), 3.0));
+        q.backward();
+        assert!(float_equal(a.grad(), 0.5));
+        assert!(float_equal(b.grad(), -1.5));
+    }
+
+    #[test]
+    fn test_backward_with_shared_label() {
+        // Two distinct constants that happen to share a label must not be
+        // merged into a single node during traversal.
+        let one_a = Value::with_label(1.0, "one");
+        let one_b = Value::with_label(1.0, "one");
+        let mut sum = one_a.add(&one_b);
+        sum.backward();
+
+        assert!(float_equal(one_a.grad(), 1.0));
+        assert!(float_equal(one_b.grad(), 1.0));
+    }
+
+    #[test]
+    fn test_backward_with_tied_weight() {
+        // The same Value reused in multiple places (weight tying) should
+        // accumulate gradient contributions from every use.
+        let w = Value::with_label(3.0, "w");
+        let x1 = Value::with_label(2.0, "x1");
+        let x2 = Value::with_label(5.0, "x2");
+        let mut out = x1.mul(&w).add(&x2.mul(&w));
+        out.backward();
+
+        assert!(float_equal(w.grad(), 7.0));
+    }
+
+    #[derive(Debug)]
+    struct SquareOp {
+        arg: Value,
+    }
+
+    impl Op for SquareOp {
+        fn forward(&self, inputs: &[Value]) -> f64 {
+            inputs[0].val() * inputs[0].val()
+        }
+
+        fn backward(&self, out_grad: f64, inputs: &[Value]) {
+            inputs[0].accumulate_grad(2.0 * inputs[0].val() * out_grad);
+        }
+
+        fn inputs(&self) -> Vec<Value> {
+            vec![self.arg.clone()]
+        }
+    }
+
+    #[test]
+    fn test_custom_op() {
+        let x = Value::with_label(3.0, "x");
+        let mut y = Value::from_op("square(x)".to_string(), Box::new(SquareOp { arg: x.clone() }));
+        assert!(float_equal(y.val(), 9.0));
+        y.backward();
+        assert!(float_equal(x.grad(), 6.0));
+    }
 }
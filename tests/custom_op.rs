@@ -0,0 +1,37 @@
+//! Exercises the scenario `Op`/`Value::from_op` exist for: a crate that
+//! depends on `micrograd` defining its own differentiable operation and
+//! dropping it into the graph without touching this crate's source.
+
+use micrograd::{Op, Value};
+
+#[derive(Debug)]
+struct SquareOp {
+    arg: Value,
+}
+
+impl Op for SquareOp {
+    fn forward(&self, inputs: &[Value]) -> f64 {
+        inputs[0].val() * inputs[0].val()
+    }
+
+    fn backward(&self, out_grad: f64, inputs: &[Value]) {
+        inputs[0].accumulate_grad(2.0 * inputs[0].val() * out_grad);
+    }
+
+    fn inputs(&self) -> Vec<Value> {
+        vec![self.arg.clone()]
+    }
+}
+
+fn float_equal(x: f64, y: f64) -> bool {
+    (x - y).abs() < 0.001
+}
+
+#[test]
+fn test_external_custom_op() {
+    let x = Value::with_label(3.0, "x");
+    let mut y = Value::from_op("square(x)".to_string(), Box::new(SquareOp { arg: x.clone() }));
+    assert!(float_equal(y.val(), 9.0));
+    y.backward();
+    assert!(float_equal(x.grad(), 6.0));
+}